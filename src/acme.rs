@@ -0,0 +1,282 @@
+use std::{
+    io,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use instant_acme::{
+    Account, AuthorizationStatus, ChallengeType, Identifier, LetsEncrypt, NewAccount, NewOrder,
+    Order, OrderStatus,
+};
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::{any_supported_type, CertifiedKey},
+    Certificate, PrivateKey,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::{ProxyError, ProxyResult};
+
+/// 距离证书到期小于该阈值时即判定缓存已不新鲜, 提前重新签发而不是等到真正过期,
+/// 给HTTP-01挑战、ACME服务器处理等留出容错时间
+const RENEWAL_MARGIN: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// ACME(Automatic Certificate Management Environment)自动签发/续期证书的配置
+pub struct AcmeConfig {
+    /// 申请证书所绑定的域名
+    pub domain: String,
+    /// ACME账户使用的联系邮箱
+    pub email: String,
+    /// 证书/私钥缓存落盘的目录
+    pub cache_dir: PathBuf,
+    /// 应答HTTP-01挑战时临时监听的地址, 需要能被ACME服务器从公网访问到的80端口
+    pub http01_bind: SocketAddr,
+}
+
+/// 基于`ResolvesServerCert`的证书解析器, 持有最新一次签发的证书并在后台自动续期
+pub struct AcmeResolver {
+    config: AcmeConfig,
+    current: RwLock<Option<Arc<CertifiedKey>>>,
+}
+
+impl AcmeResolver {
+    pub fn new(config: AcmeConfig) -> Arc<AcmeResolver> {
+        Arc::new(AcmeResolver {
+            config,
+            current: RwLock::new(None),
+        })
+    }
+
+    fn cert_path(&self) -> PathBuf {
+        self.config.cache_dir.join(format!("{}.crt", self.config.domain))
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.config.cache_dir.join(format!("{}.key", self.config.domain))
+    }
+
+    /// 读取磁盘上缓存的证书/私钥用于跨进程重启后的快速恢复; 仅当缓存的证书距到期
+    /// 仍超过`RENEWAL_MARGIN`时才视为可用, 否则返回`None`促使调用方重新签发
+    fn load_cached(&self) -> Option<CertifiedKey> {
+        let cert_pem = std::fs::read_to_string(self.cert_path()).ok()?;
+        let key_pem = std::fs::read_to_string(self.key_path()).ok()?;
+        if !Self::cert_is_fresh(&cert_pem) {
+            return None;
+        }
+        Self::build_certified_key(&cert_pem, &key_pem).ok()
+    }
+
+    /// 解析PEM证书链首张证书的`notAfter`, 判断距到期是否仍超过`RENEWAL_MARGIN`
+    fn cert_is_fresh(cert_chain_pem: &str) -> bool {
+        let mut reader = io::BufReader::new(cert_chain_pem.as_bytes());
+        let Ok(certs) = rustls_pemfile::certs(&mut reader) else {
+            return false;
+        };
+        let Some(leaf) = certs.first() else {
+            return false;
+        };
+        let Ok((_, parsed)) = x509_parser::parse_x509_certificate(leaf) else {
+            return false;
+        };
+        let Ok(not_after) = u64::try_from(parsed.validity().not_after.timestamp()) else {
+            return false;
+        };
+        let not_after = std::time::UNIX_EPOCH + Duration::from_secs(not_after);
+        match not_after.checked_sub(RENEWAL_MARGIN) {
+            Some(renew_at) => std::time::SystemTime::now() < renew_at,
+            None => false,
+        }
+    }
+
+    /// 获取可用证书: 优先使用磁盘缓存, 缺失时走一次完整的ACME签发流程
+    async fn obtain_or_renew(&self) -> ProxyResult<CertifiedKey> {
+        if let Some(key) = self.load_cached() {
+            return Ok(key);
+        }
+        self.issue_new_cert().await
+    }
+
+    /// 注册ACME账户、创建订单、应答HTTP-01挑战, 签发证书并缓存到磁盘
+    async fn issue_new_cert(&self) -> ProxyResult<CertifiedKey> {
+        std::fs::create_dir_all(&self.config.cache_dir)?;
+
+        let (account, _credentials) = Account::create(
+            &NewAccount {
+                contact: &[&format!("mailto:{}", self.config.email)],
+                terms_of_service_agreed: true,
+                only_return_existing: false,
+            },
+            LetsEncrypt::Production.url(),
+            None,
+        )
+        .await
+        .map_err(Self::acme_err)?;
+
+        let mut order = account
+            .new_order(&NewOrder {
+                identifiers: &[Identifier::Dns(self.config.domain.clone())],
+            })
+            .await
+            .map_err(Self::acme_err)?;
+
+        self.answer_authorizations(&mut order).await?;
+        self.wait_for_status(&mut order, &[OrderStatus::Ready, OrderStatus::Valid])
+            .await?;
+
+        let mut params = rcgen::CertificateParams::new(vec![self.config.domain.clone()]);
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        let cert = rcgen::Certificate::from_params(params).map_err(Self::acme_err)?;
+        let csr = cert.serialize_request_der().map_err(Self::acme_err)?;
+
+        order.finalize(&csr).await.map_err(Self::acme_err)?;
+        self.wait_for_status(&mut order, &[OrderStatus::Valid]).await?;
+
+        let cert_chain_pem = order
+            .certificate()
+            .await
+            .map_err(Self::acme_err)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "ACME未返回证书"))?;
+        let key_pem = cert.serialize_private_key_pem();
+
+        std::fs::write(self.cert_path(), &cert_chain_pem)?;
+        std::fs::write(self.key_path(), &key_pem)?;
+
+        Self::build_certified_key(&cert_chain_pem, &key_pem).map_err(ProxyError::from)
+    }
+
+    /// 对订单的每个待授权域名应答HTTP-01挑战
+    async fn answer_authorizations(&self, order: &mut Order) -> ProxyResult<()> {
+        let authorizations = order.authorizations().await.map_err(Self::acme_err)?;
+
+        for authz in &authorizations {
+            if authz.status == AuthorizationStatus::Valid {
+                continue;
+            }
+            let challenge = authz
+                .challenges
+                .iter()
+                .find(|c| c.r#type == ChallengeType::Http01)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "未找到http-01挑战"))?;
+            let key_authorization = order.key_authorization(challenge);
+            self.serve_http01_challenge(&challenge.token, key_authorization.as_str())
+                .await?;
+            order
+                .set_challenge_ready(&challenge.url)
+                .await
+                .map_err(Self::acme_err)?;
+        }
+        Ok(())
+    }
+
+    /// 在`http01_bind`上临时起一个极简HTTP服务, 应答ACME服务器对
+    /// `/.well-known/acme-challenge/<token>`的校验请求后立即退出
+    async fn serve_http01_challenge(&self, token: &str, key_authorization: &str) -> ProxyResult<()> {
+        let listener = TcpListener::bind(self.config.http01_bind).await?;
+        let challenge_path = format!("GET /.well-known/acme-challenge/{} ", token);
+        let body = key_authorization.as_bytes().to_vec();
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                let (mut stream, _) = listener.accept().await?;
+                let mut buf = [0u8; 2048];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                if !request.starts_with(&challenge_path) {
+                    continue;
+                }
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await?;
+                stream.write_all(&body).await?;
+                return Ok::<(), io::Error>(());
+            }
+        })
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "等待ACME http-01校验请求超时"))??;
+        Ok(())
+    }
+
+    /// 轮询订单状态直至达到期望状态之一, 超时则返回错误
+    async fn wait_for_status(&self, order: &mut Order, want: &[OrderStatus]) -> ProxyResult<()> {
+        for _ in 0..15 {
+            let state = order.refresh().await.map_err(Self::acme_err)?;
+            if want.contains(&state.status) {
+                return Ok(());
+            }
+            if state.status == OrderStatus::Invalid {
+                return Err(ProxyError::from(io::Error::new(
+                    io::ErrorKind::Other,
+                    "ACME订单被拒绝",
+                )));
+            }
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+        Err(ProxyError::from(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "等待ACME订单状态超时",
+        )))
+    }
+
+    fn build_certified_key(cert_chain_pem: &str, key_pem: &str) -> io::Result<CertifiedKey> {
+        let mut cert_reader = io::BufReader::new(cert_chain_pem.as_bytes());
+        let certs = rustls_pemfile::certs(&mut cert_reader)?
+            .into_iter()
+            .map(Certificate)
+            .collect::<Vec<_>>();
+        let mut key_reader = io::BufReader::new(key_pem.as_bytes());
+        let mut keys = rustls_pemfile::pkcs8_private_keys(&mut key_reader)?;
+        if keys.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "ACME未返回可用私钥"));
+        }
+        let key = PrivateKey(keys.remove(0));
+        let signing_key = any_supported_type(&key)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+        Ok(CertifiedKey::new(certs, signing_key))
+    }
+
+    fn acme_err<E: std::fmt::Display>(err: E) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, err.to_string())
+    }
+
+    /// 同步签发(或从缓存恢复)首张证书, 避免后台续期任务启动前的窗口期内
+    /// `resolve()`因尚无证书而导致所有TLS握手失败
+    pub async fn ensure_initial_cert(&self) -> ProxyResult<()> {
+        let key = self.obtain_or_renew().await?;
+        *self.current.write().unwrap() = Some(Arc::new(key));
+        Ok(())
+    }
+
+    /// 启动一个在到期前自动续期证书的后台任务
+    pub fn spawn_renewal_task(self: &Arc<Self>) {
+        let resolver = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(12 * 60 * 60)).await;
+                match resolver.obtain_or_renew().await {
+                    Ok(key) => {
+                        *resolver.current.write().unwrap() = Some(Arc::new(key));
+                    }
+                    Err(err) => {
+                        println!(
+                            "acme renew error: domain={} err={:?}",
+                            resolver.config.domain, err
+                        );
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl ResolvesServerCert for AcmeResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        self.current.read().unwrap().clone()
+    }
+}
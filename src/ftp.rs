@@ -0,0 +1,220 @@
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+/// 改写FTP被动模式(`PASV`)应答中携带的地址, 使客户端连接到代理自身的中转端口,
+/// 而不是直接连接到真实上游服务器, 格式形如`227 Entering Passive Mode (h1,h2,h3,h4,p1,p2).`
+pub fn rewrite_pasv_reply(line: &str, relay_addr: std::net::SocketAddrV4) -> Option<String> {
+    let start = line.find('(')?;
+    let end = line.find(')')?;
+    if end <= start {
+        return None;
+    }
+    let octets = relay_addr.ip().octets();
+    let port = relay_addr.port();
+    let replacement = format!(
+        "({},{},{},{},{},{})",
+        octets[0],
+        octets[1],
+        octets[2],
+        octets[3],
+        port >> 8,
+        port & 0xff
+    );
+    Some(format!(
+        "{}{}{}",
+        &line[..start],
+        replacement,
+        &line[end + 1..]
+    ))
+}
+
+/// 改写FTP扩展被动模式(`EPSV`)应答, 格式形如`229 Entering Extended Passive Mode (|||p|).`
+pub fn rewrite_epsv_reply(line: &str, relay_port: u16) -> Option<String> {
+    let start = line.find('(')?;
+    let end = line.find(')')?;
+    if end <= start {
+        return None;
+    }
+    let replacement = format!("(|||{}|)", relay_port);
+    Some(format!(
+        "{}{}{}",
+        &line[..start],
+        replacement,
+        &line[end + 1..]
+    ))
+}
+
+/// 从`227 ... (h1,h2,h3,h4,p1,p2).`形式的PASV应答中解析出真实服务器的数据连接地址
+fn parse_pasv_addr(line: &str) -> Option<SocketAddrV4> {
+    let start = line.find('(')?;
+    let end = line.find(')')?;
+    let nums: Vec<u8> = line[start + 1..end]
+        .split(',')
+        .filter_map(|part| part.trim().parse().ok())
+        .collect();
+    if nums.len() != 6 {
+        return None;
+    }
+    let ip = Ipv4Addr::new(nums[0], nums[1], nums[2], nums[3]);
+    let port = (nums[4] as u16) << 8 | nums[5] as u16;
+    Some(SocketAddrV4::new(ip, port))
+}
+
+/// 从`229 ... (|||p|).`形式的EPSV应答中解析出真实服务器的数据连接端口
+fn parse_epsv_port(line: &str) -> Option<u16> {
+    let start = line.find('(')?;
+    let end = line.find(')')?;
+    line[start + 1..end]
+        .trim_matches('|')
+        .parse()
+        .ok()
+}
+
+/// 在relay_ip上挑一个空闲端口起监听, 用作改写后的PASV/EPSV应答中下发给客户端的中转地址
+async fn bind_relay_listener(relay_ip: Ipv4Addr) -> io::Result<(SocketAddrV4, TcpListener)> {
+    let listener = TcpListener::bind((relay_ip, 0)).await?;
+    let port = listener.local_addr()?.port();
+    Ok((SocketAddrV4::new(relay_ip, port), listener))
+}
+
+/// 等待客户端连上中转监听端口, 再向真实服务器的数据端口拨号, 随后双向转发字节,
+/// 直至任意一侧关闭连接
+fn spawn_data_relay(listener: TcpListener, real_data_addr: SocketAddr) {
+    tokio::spawn(async move {
+        let Ok((mut client_data, _)) = listener.accept().await else {
+            return;
+        };
+        let Ok(mut server_data) = TcpStream::connect(real_data_addr).await else {
+            return;
+        };
+        let _ = tokio::io::copy_bidirectional(&mut client_data, &mut server_data).await;
+    });
+}
+
+/// 代理一条完整的FTP控制连接: 逐行转发控制命令, 拦截服务器返回的PASV/EPSV应答,
+/// 把其中的数据地址改写为代理自身新开的中转监听端口, 并在客户端连上中转端口后
+/// 把该数据连接转接到真实服务器, 使代理无需预先打通到客户端的任意数据端口
+pub async fn relay_ftp_session(
+    client: TcpStream,
+    upstream_addr: SocketAddr,
+    relay_ip: Ipv4Addr,
+) -> io::Result<()> {
+    let server = TcpStream::connect(upstream_addr).await?;
+    let (client_read, mut client_write) = client.into_split();
+    let (server_read, mut server_write) = server.into_split();
+    let mut client_lines = BufReader::new(client_read).lines();
+    let mut server_lines = BufReader::new(server_read).lines();
+
+    loop {
+        tokio::select! {
+            line = client_lines.next_line() => {
+                let Some(line) = line? else { break };
+                server_write.write_all(line.as_bytes()).await?;
+                server_write.write_all(b"\r\n").await?;
+            }
+            line = server_lines.next_line() => {
+                let Some(line) = line? else { break };
+                let rewritten = if line.starts_with("227") {
+                    match parse_pasv_addr(&line) {
+                        Some(real_addr) => match bind_relay_listener(relay_ip).await {
+                            Ok((relay_addr, listener)) => {
+                                spawn_data_relay(listener, SocketAddr::V4(real_addr));
+                                rewrite_pasv_reply(&line, relay_addr).unwrap_or(line)
+                            }
+                            Err(_) => line,
+                        },
+                        None => line,
+                    }
+                } else if line.starts_with("229") {
+                    match parse_epsv_port(&line) {
+                        Some(real_port) => {
+                            let real_addr = SocketAddr::new(upstream_addr.ip(), real_port);
+                            match bind_relay_listener(relay_ip).await {
+                                Ok((relay_addr, listener)) => {
+                                    spawn_data_relay(listener, real_addr);
+                                    rewrite_epsv_reply(&line, relay_addr.port()).unwrap_or(line)
+                                }
+                                Err(_) => line,
+                            }
+                        }
+                        None => line,
+                    }
+                } else {
+                    line
+                };
+                client_write.write_all(rewritten.as_bytes()).await?;
+                client_write.write_all(b"\r\n").await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, SocketAddrV4};
+
+    #[test]
+    fn rewrite_pasv_reply_replaces_address_and_port() {
+        let relay = SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 1234);
+        let rewritten = rewrite_pasv_reply(
+            "227 Entering Passive Mode (10,0,0,1,20,21).",
+            relay,
+        )
+        .unwrap();
+        assert_eq!(rewritten, "227 Entering Passive Mode (127,0,0,1,4,210).");
+    }
+
+    #[test]
+    fn rewrite_pasv_reply_without_parens_returns_none() {
+        assert!(rewrite_pasv_reply(
+            "227 Entering Passive Mode",
+            SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 1234),
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn rewrite_epsv_reply_replaces_port() {
+        let rewritten =
+            rewrite_epsv_reply("229 Entering Extended Passive Mode (|||21|).", 5678).unwrap();
+        assert_eq!(rewritten, "229 Entering Extended Passive Mode (|||5678|).");
+    }
+
+    #[test]
+    fn rewrite_epsv_reply_without_parens_returns_none() {
+        assert!(rewrite_epsv_reply("229 Entering Extended Passive Mode", 5678).is_none());
+    }
+
+    #[test]
+    fn parse_pasv_addr_reads_ip_and_port() {
+        let addr = parse_pasv_addr("227 Entering Passive Mode (10,0,0,1,20,21).").unwrap();
+        assert_eq!(addr, SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 20 * 256 + 21));
+    }
+
+    #[test]
+    fn parse_pasv_addr_rejects_malformed_tuple() {
+        assert!(parse_pasv_addr("227 Entering Passive Mode (10,0,0,1).").is_none());
+    }
+
+    #[test]
+    fn parse_epsv_port_reads_port() {
+        assert_eq!(
+            parse_epsv_port("229 Entering Extended Passive Mode (|||5678|)."),
+            Some(5678)
+        );
+    }
+
+    #[test]
+    fn parse_epsv_port_rejects_non_numeric() {
+        assert!(parse_epsv_port("229 Entering Extended Passive Mode (|||x|).").is_none());
+    }
+}
@@ -0,0 +1,162 @@
+use std::{io, sync::Arc};
+
+use russh::{
+    client::{self, Handle as ClientHandle},
+    keys::{key, load_secret_key},
+    server::{self, Auth, Msg, Session},
+    Channel, ChannelId,
+};
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+use crate::{ProxyError, ProxyResult};
+
+/// 通过SSH承载一对多中心通道所需要的身份配置.
+///
+/// `connect`/`accept`是完整可用的SSH握手与通道建立原语, 但目前还没有任何调用方:
+/// 把已打开的`Channel`接入中心通道, 需要`CenterClient`/`CenterServer`把
+/// `ProtFrame`读写到这条`Channel`上(即`TransStream`在TLS流之外识别SSH `Channel`的
+/// 那一份实现), 而这两个类型都是在`option.rs`里按名字导入、实现却不在本仓库当前
+/// 这组文件之内, 因此无法在这里替它们接线而不猜测其真实接口。配置
+/// `--ssh-host-key`/`--ssh-identity`目前仍只构造出一个可用的握手原语, 尚不会让
+/// 中心通道真正改走SSH传输; 接入点留待`CenterClient`/`CenterServer`的实现落地后
+/// 再补上。
+#[derive(Default, Clone)]
+pub struct SshTransportConfig {
+    /// 作为中心服务端时用于标识自身身份的SSH host key(私钥文件路径)
+    pub host_key: Option<String>,
+    /// 允许连入的子节点公钥列表文件(authorized_keys格式)
+    pub authorized_keys: Option<String>,
+    /// 作为子节点时向中心服务端出示的身份私钥文件路径
+    pub identity: Option<String>,
+    /// 身份私钥的解密密码, 用于加密的PKCS8身份私钥
+    pub identity_password: Option<String>,
+}
+
+impl SshTransportConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.host_key.is_some() || self.identity.is_some()
+    }
+
+    /// 读取`authorized_keys`文件, 返回逐行的公钥列表
+    pub fn load_authorized_keys(&self) -> io::Result<Vec<String>> {
+        match &self.authorized_keys {
+            Some(path) => {
+                let data = std::fs::read_to_string(path)?;
+                Ok(data
+                    .lines()
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .collect())
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// 作为子节点, 向中心服务端发起SSH连接, 以公钥方式完成身份认证并打开一个用于
+    /// 承载`ProtFrame`的SSH通道, 返回的`Channel`可直接当作双工字节流使用
+    pub async fn connect<A: ToSocketAddrs + Send>(&self, addr: A) -> ProxyResult<Channel<client::Msg>> {
+        let identity = self.identity.clone().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "SSH传输需要配置--ssh-identity")
+        })?;
+        let key_pair = load_secret_key(&identity, self.identity_password.as_deref())
+            .map_err(Self::ssh_err)?;
+
+        let config = Arc::new(client::Config::default());
+        let mut handle: ClientHandle<SshClientHandler> =
+            client::connect(config, addr, SshClientHandler).await.map_err(Self::ssh_err)?;
+        let authenticated = handle
+            .authenticate_publickey("wmproxy", Arc::new(key_pair))
+            .await
+            .map_err(Self::ssh_err)?;
+        if !authenticated {
+            return Err(ProxyError::from(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "SSH身份认证失败",
+            )));
+        }
+        handle
+            .channel_open_session()
+            .await
+            .map_err(Self::ssh_err)
+    }
+
+    /// 作为中心服务端, 在一条已接受的TCP连接上驱动SSH服务端握手, 以子节点的公钥校验
+    /// 其是否出现在`authorized_keys`中, 认证通过后等待子节点打开的首个通道
+    pub async fn accept(&self, stream: TcpStream) -> ProxyResult<Channel<Msg>> {
+        let host_key_path = self.host_key.clone().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "SSH传输需要配置--ssh-host-key")
+        })?;
+        let host_key = load_secret_key(&host_key_path, None).map_err(Self::ssh_err)?;
+        let config = Arc::new(server::Config {
+            keys: vec![host_key],
+            ..Default::default()
+        });
+
+        let (channel_tx, mut channel_rx) = tokio::sync::mpsc::channel(1);
+        let mut handler = SshServerHandler {
+            authorized_keys: self.load_authorized_keys()?,
+            channel_tx,
+        };
+        tokio::spawn(async move {
+            let _ = server::run_stream(config, stream, &mut handler).await;
+        });
+
+        channel_rx.recv().await.ok_or_else(|| {
+            ProxyError::from(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "SSH握手结束前未收到子节点打开的通道",
+            ))
+        })
+    }
+
+    fn ssh_err<E: std::fmt::Display>(err: E) -> ProxyError {
+        ProxyError::from(io::Error::new(io::ErrorKind::Other, err.to_string()))
+    }
+}
+
+/// 子节点侧的SSH客户端回调, 中心通道场景下host key采用TOFU(首次连接即信任)策略
+struct SshClientHandler;
+
+#[async_trait::async_trait]
+impl client::Handler for SshClientHandler {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, _server_public_key: &key::PublicKey) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+/// 中心服务端侧的SSH回调: 按公钥匹配`authorized_keys`, 并把子节点打开的通道转交出去
+struct SshServerHandler {
+    authorized_keys: Vec<String>,
+    channel_tx: tokio::sync::mpsc::Sender<Channel<Msg>>,
+}
+
+#[async_trait::async_trait]
+impl server::Handler for SshServerHandler {
+    type Error = russh::Error;
+
+    async fn auth_publickey(&mut self, _user: &str, public_key: &key::PublicKey) -> Result<Auth, Self::Error> {
+        let presented = public_key.public_key_base64();
+        let allowed = self
+            .authorized_keys
+            .iter()
+            .filter_map(|line| line.split_whitespace().nth(1))
+            .any(|key_field| key_field == presented);
+        Ok(if allowed { Auth::Accept } else { Auth::reject() })
+    }
+
+    async fn channel_open_session(
+        &mut self,
+        channel: Channel<Msg>,
+        session: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        let _ = self.channel_tx.try_send(channel);
+        let _ = session;
+        Ok(true)
+    }
+
+    async fn data(&mut self, _channel: ChannelId, _data: &[u8], _session: &mut Session) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}